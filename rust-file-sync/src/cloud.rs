@@ -1,6 +1,36 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use rusoto_s3::{
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload,
+    CompletedPart, CreateMultipartUploadRequest, DeleteObjectRequest, GetObjectError,
+    GetObjectRequest, ListObjectsV2Request, PutObjectRequest, S3, UploadPartRequest,
+};
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 
+/// Uploads at or above this size use S3 multipart upload instead of a
+/// single `PutObject` call.
+const MULTIPART_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Size of each part in a multipart upload. S3 requires every part but the
+/// last to be at least 5 MiB.
+const MULTIPART_PART_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Marker error returned by `download_file` when the remote object genuinely
+/// doesn't exist, so callers can tell that apart from other failures
+/// (network errors, throttling, permission errors) that must not be treated
+/// as "nothing uploaded yet". Check for it with `error.downcast_ref::<NotFound>()`.
+#[derive(Debug)]
+pub struct NotFound;
+
+impl std::fmt::Display for NotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "remote object does not exist")
+    }
+}
+
+impl std::error::Error for NotFound {}
+
 /// Trait defining cloud provider operations
 pub trait CloudProvider {
     fn upload_file(&self, local_path: &Path, remote_path: &str) -> Result<()>;
@@ -11,7 +41,9 @@ pub trait CloudProvider {
 
 /// Google Drive cloud provider implementation
 pub struct GoogleDriveProvider {
-    client: google_drive3::DriveHub,
+    // Not read yet: the methods below are placeholders until real Drive API
+    // calls are wired in, at which point this selects the target folder.
+    #[allow(dead_code)]
     folder_id: String,
 }
 
@@ -19,15 +51,8 @@ impl GoogleDriveProvider {
     pub fn new(config: &crate::config::GoogleDriveConfig) -> Result<Self> {
         // This is a simplified implementation
         // In a real application, you would use OAuth2 to authenticate with Google Drive
-        
-        // For now, we'll just create a placeholder
-        let client = google_drive3::DriveHub::new(
-            reqwest::Client::new(),
-            yup_oauth2::authenticator::Authenticator::default(),
-        );
-        
+        // and hold onto the resulting client here.
         Ok(Self {
-            client,
             folder_id: config.folder_id.clone(),
         })
     }
@@ -73,87 +98,304 @@ impl CloudProvider for GoogleDriveProvider {
 pub struct S3Provider {
     client: rusoto_s3::S3Client,
     bucket: String,
+    // rusoto's client is async-only; the rest of the application (and the
+    // `CloudProvider` trait) is synchronous, so we drive each call to
+    // completion on a dedicated runtime instead of threading async through
+    // the whole app.
+    runtime: tokio::runtime::Runtime,
 }
 
 impl S3Provider {
     pub fn new(config: &crate::config::S3Config) -> Result<Self> {
-        // Create AWS credentials provider
-        let credentials_provider = rusoto_core::credential::StaticProvider::new(
-            config.access_key.clone(),
-            config.secret_key.clone(),
-            None,
-            None,
-        );
-        
-        // Create S3 client
-        let region = rusoto_core::Region::from_str(&config.region)?;
-        let client = rusoto_s3::S3Client::new_with(
-            rusoto_core::HttpClient::new()?,
-            credentials_provider,
-            region,
-        );
-        
+        let region = parse_region(&config.region)?;
+        let client = Self::build_client(config, region)?;
+
+        let runtime = tokio::runtime::Runtime::new()
+            .context("Failed to create async runtime for S3 client")?;
+
         Ok(Self {
             client,
             bucket: config.bucket.clone(),
+            runtime,
         })
     }
+
+    /// Resolve AWS credentials and build the S3 client. Explicit
+    /// `access_key`/`secret_key` in the config take priority (so existing
+    /// configs keep working); otherwise fall back to the standard AWS
+    /// credential chain: `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY`/
+    /// `AWS_SESSION_TOKEN` environment variables, then the `~/.aws/credentials`
+    /// profile, then EC2/ECS instance metadata for temporary role
+    /// credentials. This lets the tool run with an IAM role and no
+    /// hardcoded secrets.
+    fn build_client(
+        config: &crate::config::S3Config,
+        region: rusoto_core::Region,
+    ) -> Result<rusoto_s3::S3Client> {
+        let http_client = rusoto_core::HttpClient::new()?;
+
+        if let (Some(access_key), Some(secret_key)) = (&config.access_key, &config.secret_key) {
+            let credentials_provider = rusoto_core::credential::StaticProvider::new(
+                access_key.clone(),
+                secret_key.clone(),
+                None,
+                None,
+            );
+            return Ok(rusoto_s3::S3Client::new_with(
+                http_client,
+                credentials_provider,
+                region,
+            ));
+        }
+
+        let credentials_provider = rusoto_core::credential::DefaultCredentialsProvider::new()
+            .context("Failed to initialize AWS credential chain")?;
+        Ok(rusoto_s3::S3Client::new_with(
+            http_client,
+            credentials_provider,
+            region,
+        ))
+    }
+
+    fn upload_single(&self, local_path: &Path, remote_path: &str) -> Result<()> {
+        let mut buffer = Vec::new();
+        File::open(local_path)
+            .with_context(|| format!("Failed to open file {:?} for upload", local_path))?
+            .read_to_end(&mut buffer)
+            .with_context(|| format!("Failed to read file {:?} for upload", local_path))?;
+
+        let request = PutObjectRequest {
+            bucket: self.bucket.clone(),
+            key: remote_path.to_string(),
+            body: Some(buffer.into()),
+            ..Default::default()
+        };
+
+        self.runtime
+            .block_on(self.client.put_object(request))
+            .context("S3 PutObject failed")?;
+
+        log::info!("Uploaded file to S3: {:?} -> {}", local_path, remote_path);
+        Ok(())
+    }
+
+    fn upload_multipart(&self, local_path: &Path, remote_path: &str, total_len: u64) -> Result<()> {
+        let create_request = CreateMultipartUploadRequest {
+            bucket: self.bucket.clone(),
+            key: remote_path.to_string(),
+            ..Default::default()
+        };
+        let create_response = self
+            .runtime
+            .block_on(self.client.create_multipart_upload(create_request))
+            .context("S3 CreateMultipartUpload failed")?;
+        let upload_id = create_response
+            .upload_id
+            .context("S3 did not return a multipart upload ID")?;
+
+        match self.upload_parts(local_path, remote_path, &upload_id, total_len) {
+            Ok(parts) => {
+                let complete_request = CompleteMultipartUploadRequest {
+                    bucket: self.bucket.clone(),
+                    key: remote_path.to_string(),
+                    upload_id: upload_id.clone(),
+                    multipart_upload: Some(CompletedMultipartUpload { parts: Some(parts) }),
+                    ..Default::default()
+                };
+                self.runtime
+                    .block_on(self.client.complete_multipart_upload(complete_request))
+                    .context("S3 CompleteMultipartUpload failed")?;
+
+                log::info!(
+                    "Uploaded file to S3 (multipart): {:?} -> {}",
+                    local_path,
+                    remote_path
+                );
+                Ok(())
+            }
+            Err(e) => {
+                // Don't leave orphaned parts behind incurring storage charges.
+                let abort_request = AbortMultipartUploadRequest {
+                    bucket: self.bucket.clone(),
+                    key: remote_path.to_string(),
+                    upload_id,
+                    ..Default::default()
+                };
+                if let Err(abort_err) = self
+                    .runtime
+                    .block_on(self.client.abort_multipart_upload(abort_request))
+                {
+                    log::error!(
+                        "Failed to abort multipart upload for {}: {}",
+                        remote_path,
+                        abort_err
+                    );
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn upload_parts(
+        &self,
+        local_path: &Path,
+        remote_path: &str,
+        upload_id: &str,
+        total_len: u64,
+    ) -> Result<Vec<CompletedPart>> {
+        let mut file = File::open(local_path)
+            .with_context(|| format!("Failed to open file {:?} for upload", local_path))?;
+
+        let mut parts = Vec::new();
+        let mut part_number: i64 = 1;
+        let mut remaining = total_len;
+
+        while remaining > 0 {
+            let this_part_len = MULTIPART_PART_SIZE.min(remaining) as usize;
+            let mut buffer = vec![0u8; this_part_len];
+            file.read_exact(&mut buffer)
+                .with_context(|| format!("Failed to read part {} of {:?}", part_number, local_path))?;
+
+            let request = UploadPartRequest {
+                bucket: self.bucket.clone(),
+                key: remote_path.to_string(),
+                upload_id: upload_id.to_string(),
+                part_number,
+                body: Some(buffer.into()),
+                ..Default::default()
+            };
+            let response = self
+                .runtime
+                .block_on(self.client.upload_part(request))
+                .with_context(|| format!("S3 UploadPart {} failed", part_number))?;
+            let e_tag = response
+                .e_tag
+                .with_context(|| format!("S3 did not return an ETag for part {}", part_number))?;
+
+            parts.push(CompletedPart {
+                e_tag: Some(e_tag),
+                part_number: Some(part_number),
+            });
+
+            remaining -= this_part_len as u64;
+            part_number += 1;
+        }
+
+        Ok(parts)
+    }
 }
 
 impl CloudProvider for S3Provider {
     fn upload_file(&self, local_path: &Path, remote_path: &str) -> Result<()> {
-        // Implement file upload to S3
-        log::info!("Uploading file to S3: {:?} -> {}", local_path, remote_path);
-        
-        // Placeholder implementation
-        Ok(())
+        let metadata = std::fs::metadata(local_path)
+            .with_context(|| format!("Failed to stat file {:?}", local_path))?;
+
+        if metadata.len() >= MULTIPART_THRESHOLD {
+            self.upload_multipart(local_path, remote_path, metadata.len())
+        } else {
+            self.upload_single(local_path, remote_path)
+        }
     }
 
     fn download_file(&self, remote_path: &str, local_path: &Path) -> Result<()> {
-        // Implement file download from S3
-        log::info!("Downloading file from S3: {} -> {:?}", remote_path, local_path);
-        
-        // Placeholder implementation
+        let request = GetObjectRequest {
+            bucket: self.bucket.clone(),
+            key: remote_path.to_string(),
+            ..Default::default()
+        };
+
+        let response = match self.runtime.block_on(self.client.get_object(request)) {
+            Ok(response) => response,
+            Err(rusoto_core::RusotoError::Service(GetObjectError::NoSuchKey(_))) => {
+                return Err(NotFound.into());
+            }
+            Err(e) => return Err(e).context("S3 GetObject failed"),
+        };
+        let body = response.body.context("S3 object has no body")?;
+
+        let mut reader = body.into_blocking_read();
+        let mut output_file = File::create(local_path)
+            .with_context(|| format!("Failed to create local file {:?}", local_path))?;
+        std::io::copy(&mut reader, &mut output_file)
+            .context("Failed to write downloaded S3 object to disk")?;
+
+        log::info!("Downloaded file from S3: {} -> {:?}", remote_path, local_path);
         Ok(())
     }
 
     fn delete_file(&self, remote_path: &str) -> Result<()> {
-        // Implement file deletion from S3
-        log::info!("Deleting file from S3: {}", remote_path);
-        
-        // Placeholder implementation
+        let request = DeleteObjectRequest {
+            bucket: self.bucket.clone(),
+            key: remote_path.to_string(),
+            ..Default::default()
+        };
+
+        self.runtime
+            .block_on(self.client.delete_object(request))
+            .context("S3 DeleteObject failed")?;
+
+        log::info!("Deleted file from S3: {}", remote_path);
         Ok(())
     }
 
     fn list_files(&self, prefix: &str) -> Result<Vec<String>> {
-        // Implement file listing from S3
-        log::info!("Listing files from S3 with prefix: {}", prefix);
-        
-        // Placeholder implementation
-        Ok(vec![])
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let request = ListObjectsV2Request {
+                bucket: self.bucket.clone(),
+                prefix: Some(prefix.to_string()),
+                continuation_token: continuation_token.clone(),
+                ..Default::default()
+            };
+
+            let response = self
+                .runtime
+                .block_on(self.client.list_objects_v2(request))
+                .context("S3 ListObjectsV2 failed")?;
+
+            keys.extend(
+                response
+                    .contents
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|object| object.key),
+            );
+
+            if response.is_truncated == Some(true) {
+                continuation_token = response.next_continuation_token;
+            } else {
+                break;
+            }
+        }
+
+        log::info!("Listed {} file(s) from S3 with prefix: {}", keys.len(), prefix);
+        Ok(keys)
     }
 }
 
-// Helper function to convert string to Region
-impl rusoto_core::Region {
-    fn from_str(s: &str) -> Result<Self> {
-        match s {
-            "us-east-1" => Ok(rusoto_core::Region::UsEast1),
-            "us-east-2" => Ok(rusoto_core::Region::UsEast2),
-            "us-west-1" => Ok(rusoto_core::Region::UsWest1),
-            "us-west-2" => Ok(rusoto_core::Region::UsWest2),
-            "ap-northeast-1" => Ok(rusoto_core::Region::ApNortheast1),
-            "ap-northeast-2" => Ok(rusoto_core::Region::ApNortheast2),
-            "ap-south-1" => Ok(rusoto_core::Region::ApSouth1),
-            "ap-southeast-1" => Ok(rusoto_core::Region::ApSoutheast1),
-            "ap-southeast-2" => Ok(rusoto_core::Region::ApSoutheast2),
-            "ca-central-1" => Ok(rusoto_core::Region::CaCentral1),
-            "eu-central-1" => Ok(rusoto_core::Region::EuCentral1),
-            "eu-west-1" => Ok(rusoto_core::Region::EuWest1),
-            "eu-west-2" => Ok(rusoto_core::Region::EuWest2),
-            "eu-west-3" => Ok(rusoto_core::Region::EuWest3),
-            "sa-east-1" => Ok(rusoto_core::Region::SaEast1),
-            _ => anyhow::bail!("Unsupported region: {}", s),
-        }
+/// Parse a region name (e.g. `"us-east-1"`) into a `rusoto_core::Region`.
+/// A free function rather than an inherent impl on `Region` itself, since
+/// `Region` is defined in `rusoto_core` and Rust's orphan rules forbid
+/// inherent impls on types from another crate.
+fn parse_region(s: &str) -> Result<rusoto_core::Region> {
+    match s {
+        "us-east-1" => Ok(rusoto_core::Region::UsEast1),
+        "us-east-2" => Ok(rusoto_core::Region::UsEast2),
+        "us-west-1" => Ok(rusoto_core::Region::UsWest1),
+        "us-west-2" => Ok(rusoto_core::Region::UsWest2),
+        "ap-northeast-1" => Ok(rusoto_core::Region::ApNortheast1),
+        "ap-northeast-2" => Ok(rusoto_core::Region::ApNortheast2),
+        "ap-south-1" => Ok(rusoto_core::Region::ApSouth1),
+        "ap-southeast-1" => Ok(rusoto_core::Region::ApSoutheast1),
+        "ap-southeast-2" => Ok(rusoto_core::Region::ApSoutheast2),
+        "ca-central-1" => Ok(rusoto_core::Region::CaCentral1),
+        "eu-central-1" => Ok(rusoto_core::Region::EuCentral1),
+        "eu-west-1" => Ok(rusoto_core::Region::EuWest1),
+        "eu-west-2" => Ok(rusoto_core::Region::EuWest2),
+        "eu-west-3" => Ok(rusoto_core::Region::EuWest3),
+        "sa-east-1" => Ok(rusoto_core::Region::SaEast1),
+        _ => anyhow::bail!("Unsupported region: {}", s),
     }
 }
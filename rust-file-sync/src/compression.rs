@@ -1,51 +1,120 @@
 use anyhow::{Context, Result};
-use flate2::{write::GzEncoder, Compression};
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-
-/// Compress a file using gzip
-pub fn compress_file(input_path: &Path, output_path: &Path) -> Result<()> {
-    // Open input file
-    let mut input_file = File::open(input_path)
-        .context("Failed to open input file for compression")?;
-    
-    // Create output file with gzip encoder
-    let output_file = File::create(output_path)
-        .context("Failed to create output file for compression")?;
-    let mut encoder = GzEncoder::new(output_file, Compression::best());
-    
-    // Read input file and write to encoder
-    let mut buffer = Vec::new();
-    input_file.read_to_end(&mut buffer)
-        .context("Failed to read input file for compression")?;
-    encoder.write_all(&buffer)
-        .context("Failed to write compressed data")?;
-    encoder.finish()
-        .context("Failed to finish compression")?;
-    
-    log::info!("Compressed file: {:?} -> {:?}", input_path, output_path);
-    Ok(())
-}
-
-/// Decompress a gzip file
-pub fn decompress_file(input_path: &Path, output_path: &Path) -> Result<()> {
-    // Open input file
-    let input_file = File::open(input_path)
-        .context("Failed to open input file for decompression")?;
-    let mut decoder = flate2::read::GzDecoder::new(input_file);
-    
-    // Create output file
-    let mut output_file = File::create(output_path)
-        .context("Failed to create output file for decompression")?;
-    
-    // Read from decoder and write to output file
-    let mut buffer = Vec::new();
-    decoder.read_to_end(&mut buffer)
-        .context("Failed to read compressed data")?;
-    output_file.write_all(&buffer)
-        .context("Failed to write decompressed data")?;
-    
-    log::info!("Decompressed file: {:?} -> {:?}", input_path, output_path);
-    Ok(())
+use flate2::{read::GzDecoder, write::GzEncoder, Compression as GzCompression};
+use serde::{Deserialize, Serialize};
+use std::io::{BufReader, Read, Write};
+
+use crate::config::CompressionConfig;
+
+/// Supported compression algorithms, selectable via `CompressionConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    #[default]
+    Gzip,
+    Zstd,
+    /// No compression; the data is passed through unchanged. Useful for
+    /// already-compressed formats (e.g. quantized model shards) where
+    /// compressing again only costs CPU.
+    None,
+}
+
+impl CompressionAlgorithm {
+    /// Short tag folded into remote object names / sidecar metadata so a
+    /// download can pick the right decoder without needing the config
+    /// that produced the upload.
+    pub fn tag(self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Gzip => "gz",
+            CompressionAlgorithm::Zstd => "zst",
+            CompressionAlgorithm::None => "raw",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Result<Self> {
+        match tag {
+            "gz" => Ok(CompressionAlgorithm::Gzip),
+            "zst" => Ok(CompressionAlgorithm::Zstd),
+            "raw" => Ok(CompressionAlgorithm::None),
+            other => anyhow::bail!("Unknown compression tag: {}", other),
+        }
+    }
+}
+
+/// Streaming compressor dispatching to the configured algorithm. Wrap any
+/// `Write` in one of these to compress everything written through it.
+pub enum CompressWriter<W: Write> {
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    None(W),
+}
+
+impl<W: Write> CompressWriter<W> {
+    pub fn new(config: &CompressionConfig, inner: W) -> Result<Self> {
+        Ok(match config.algorithm {
+            CompressionAlgorithm::Gzip => {
+                let level = config.level.clamp(0, 9) as u32;
+                CompressWriter::Gzip(GzEncoder::new(inner, GzCompression::new(level)))
+            }
+            CompressionAlgorithm::Zstd => CompressWriter::Zstd(
+                zstd::Encoder::new(inner, config.level).context("Failed to init zstd encoder")?,
+            ),
+            CompressionAlgorithm::None => CompressWriter::None(inner),
+        })
+    }
+
+    /// Finish the stream and hand back the underlying writer.
+    pub fn finish(self) -> Result<W> {
+        match self {
+            CompressWriter::Gzip(w) => w.finish().context("Failed to finish gzip stream"),
+            CompressWriter::Zstd(w) => w.finish().context("Failed to finish zstd stream"),
+            CompressWriter::None(w) => Ok(w),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            CompressWriter::Gzip(w) => w.write(buf),
+            CompressWriter::Zstd(w) => w.write(buf),
+            CompressWriter::None(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            CompressWriter::Gzip(w) => w.flush(),
+            CompressWriter::Zstd(w) => w.flush(),
+            CompressWriter::None(w) => w.flush(),
+        }
+    }
+}
+
+/// Streaming decompressor, the inverse of [`CompressWriter`].
+pub enum CompressReader<R: Read> {
+    Gzip(GzDecoder<R>),
+    Zstd(zstd::Decoder<'static, BufReader<R>>),
+    None(R),
+}
+
+impl<R: Read> CompressReader<R> {
+    pub fn new(algorithm: CompressionAlgorithm, inner: R) -> Result<Self> {
+        Ok(match algorithm {
+            CompressionAlgorithm::Gzip => CompressReader::Gzip(GzDecoder::new(inner)),
+            CompressionAlgorithm::Zstd => CompressReader::Zstd(
+                zstd::Decoder::new(inner).context("Failed to init zstd decoder")?,
+            ),
+            CompressionAlgorithm::None => CompressReader::None(inner),
+        })
+    }
+}
+
+impl<R: Read> Read for CompressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            CompressReader::Gzip(r) => r.read(buf),
+            CompressReader::Zstd(r) => r.read(buf),
+            CompressReader::None(r) => r.read(buf),
+        }
+    }
 }
@@ -18,6 +18,21 @@ pub struct AppConfig {
     
     /// Encryption configuration
     pub encryption: EncryptionConfig,
+
+    /// Object versioning configuration
+    #[serde(default)]
+    pub versioning: VersioningConfig,
+
+    /// Allow loading secret/credential files even if they are group- or
+    /// world-readable. Defaults to `false`; prefer fixing file permissions
+    /// over setting this. The `ALLOW_WORLD_READABLE_SECRETS` environment
+    /// variable always takes precedence over this field.
+    #[serde(default)]
+    pub allow_world_readable_secrets: bool,
+
+    /// Compression algorithm and level for the upload pipeline
+    #[serde(default)]
+    pub compression: CompressionConfig,
 }
 
 /// Google Drive configuration
@@ -38,12 +53,15 @@ pub struct S3Config {
     
     /// AWS region
     pub region: String,
-    
-    /// AWS access key
-    pub access_key: String,
-    
-    /// AWS secret key
-    pub secret_key: String,
+
+    /// AWS access key. Optional: when absent, credentials are resolved
+    /// from the environment, `~/.aws/credentials`, or instance metadata.
+    #[serde(default)]
+    pub access_key: Option<String>,
+
+    /// AWS secret key. Optional, see `access_key`.
+    #[serde(default)]
+    pub secret_key: Option<String>,
 }
 
 /// Encryption configuration
@@ -53,6 +71,94 @@ pub struct EncryptionConfig {
     pub key: String,
 }
 
+/// Object versioning configuration. When enabled, uploads don't overwrite
+/// the previous copy of a logical object; instead each upload gets its own
+/// generation, with older generations pruned beyond `retention_count`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VersioningConfig {
+    /// Keep previous generations instead of overwriting them on upload.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Number of newest generations to retain per object; older ones are
+    /// pruned after each upload. `None` keeps every generation.
+    #[serde(default)]
+    pub retention_count: Option<u32>,
+}
+
+/// Compression configuration for the upload pipeline. Defaults to gzip, the
+/// algorithm used before this option existed, at level 6.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    /// Compression algorithm: `gzip`, `zstd`, or `none`.
+    #[serde(default)]
+    pub algorithm: crate::compression::CompressionAlgorithm,
+
+    /// Compression level. Interpreted per algorithm (gzip: 0-9, zstd:
+    /// typically 1-22); ignored when `algorithm` is `none`.
+    #[serde(default = "default_compression_level")]
+    pub level: i32,
+}
+
+fn default_compression_level() -> i32 {
+    6
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: crate::compression::CompressionAlgorithm::default(),
+            level: default_compression_level(),
+        }
+    }
+}
+
+/// Environment variable that always overrides `allow_world_readable_secrets`,
+/// for static config files that can't be chmod'd.
+const ALLOW_WORLD_READABLE_ENV: &str = "ALLOW_WORLD_READABLE_SECRETS";
+
+fn allow_world_readable_env_override() -> Option<bool> {
+    std::env::var(ALLOW_WORLD_READABLE_ENV)
+        .ok()
+        .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+/// Reject group- or world-readable secret/credential files unless
+/// explicitly allowed. A no-op on non-Unix platforms.
+#[cfg(unix)]
+fn check_secret_file_permissions(path: &Path, allow_world_readable: bool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if allow_world_readable || !path.exists() {
+        // A missing file is a separate problem for the caller to surface;
+        // this guard only cares about files that do exist insecurely.
+        return Ok(());
+    }
+
+    let metadata = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat secret file {:?}", path))?;
+    let mode = metadata.permissions().mode() & 0o777;
+
+    if mode & 0o077 != 0 {
+        anyhow::bail!(
+            "Refusing to read {:?}: file is group- or world-readable (mode {:o}). \
+             Run `chmod 600 {:?}`, or set `allow_world_readable_secrets` / the \
+             {} environment variable to override.",
+            path,
+            mode,
+            path,
+            ALLOW_WORLD_READABLE_ENV
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_secret_file_permissions(_path: &Path, _allow_world_readable: bool) -> Result<()> {
+    Ok(())
+}
+
 /// Load configuration from a file
 pub fn load_config(path: &Path) -> Result<AppConfig> {
     // Read configuration file
@@ -61,15 +167,28 @@ pub fn load_config(path: &Path) -> Result<AppConfig> {
     let mut contents = String::new();
     file.read_to_string(&mut contents)
         .context("Failed to read configuration file")?;
-    
+
     // Parse configuration
     let config: AppConfig = toml::from_str(&contents)
         .context("Failed to parse configuration file")?;
-    
+
+    // config.toml carries encryption.key and (optionally) AWS secrets in
+    // plaintext, so guard it the same way as any other credential file.
+    let allow_world_readable =
+        allow_world_readable_env_override().unwrap_or(config.allow_world_readable_secrets);
+    check_secret_file_permissions(path, allow_world_readable)
+        .context("Configuration file failed permission check")?;
+    check_secret_file_permissions(
+        Path::new(&config.google_drive.credentials_file),
+        allow_world_readable,
+    )
+    .context("Google Drive credentials file failed permission check")?;
+
     Ok(config)
 }
 
 /// Create a default configuration
+#[allow(dead_code)]
 pub fn create_default_config() -> AppConfig {
     AppConfig {
         remote_base_path: "ai-models".to_string(),
@@ -80,22 +199,83 @@ pub fn create_default_config() -> AppConfig {
         s3: S3Config {
             bucket: "your-bucket".to_string(),
             region: "us-east-1".to_string(),
-            access_key: "your-access-key".to_string(),
-            secret_key: "your-secret-key".to_string(),
+            // Left unset so the default config works out of the box on
+            // EC2/ECS with an IAM role; set both to pin static credentials.
+            access_key: None,
+            secret_key: None,
         },
         encryption: EncryptionConfig {
             key: "your-encryption-key".to_string(),
         },
+        versioning: VersioningConfig {
+            enabled: false,
+            retention_count: Some(5),
+        },
+        allow_world_readable_secrets: false,
+        compression: CompressionConfig::default(),
     }
 }
 
 /// Save configuration to a file
+#[allow(dead_code)]
 pub fn save_config(config: &AppConfig, path: &Path) -> Result<()> {
     let contents = toml::to_string_pretty(config)
         .context("Failed to serialize configuration")?;
-    
+
     std::fs::write(path, contents)
         .context("Failed to write configuration file")?;
-    
+
     Ok(())
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn temp_file_with_mode(name: &str, mode: u32) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("config-test-{}-{}", std::process::id(), name));
+        std::fs::write(&path, b"secret").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn rejects_world_readable_file() {
+        let path = temp_file_with_mode("world-readable", 0o644);
+        assert!(check_secret_file_permissions(&path, false).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn accepts_owner_only_file() {
+        let path = temp_file_with_mode("owner-only", 0o600);
+        assert!(check_secret_file_permissions(&path, false).is_ok());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn allow_world_readable_flag_bypasses_the_check() {
+        let path = temp_file_with_mode("bypassed", 0o644);
+        assert!(check_secret_file_permissions(&path, true).is_ok());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn missing_file_is_not_this_guards_problem() {
+        let path = std::env::temp_dir().join("config-test-does-not-exist.secret");
+        assert!(check_secret_file_permissions(&path, false).is_ok());
+    }
+
+    #[test]
+    fn env_override_parses_truthy_and_falsy_values() {
+        std::env::set_var(ALLOW_WORLD_READABLE_ENV, "true");
+        assert_eq!(allow_world_readable_env_override(), Some(true));
+
+        std::env::set_var(ALLOW_WORLD_READABLE_ENV, "0");
+        assert_eq!(allow_world_readable_env_override(), Some(false));
+
+        std::env::remove_var(ALLOW_WORLD_READABLE_ENV);
+        assert_eq!(allow_world_readable_env_override(), None);
+    }
+}
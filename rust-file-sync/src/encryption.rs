@@ -1,5 +1,5 @@
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
 use anyhow::{Context, Result};
@@ -8,6 +8,16 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
 
+/// Size of the random per-file salt folded into every chunk's nonce.
+const SALT_LEN: usize = 8;
+
+/// Plaintext chunk size for the streaming AEAD framing (64 KiB).
+///
+/// AES-GCM must never encrypt unbounded data under a single nonce, so large
+/// files are split into chunks, each sealed with its own nonce derived from
+/// the file salt plus a monotonic counter.
+const CHUNK_SIZE: usize = 64 * 1024;
+
 /// AES-256 encryption implementation
 pub struct AesEncryption {
     cipher: Aes256Gcm,
@@ -34,72 +44,365 @@ impl AesEncryption {
         Ok(Self { cipher })
     }
 
-    /// Encrypt a file
+    /// Wrap `inner` in a chunked AEAD writer. Every `CHUNK_SIZE` bytes of
+    /// plaintext written through it are sealed as one framed segment; call
+    /// [`ChunkEncryptor::finish`] once writing is done to seal the final
+    /// (possibly short) segment.
+    pub fn chunk_encryptor<W: Write>(&self, inner: W) -> Result<ChunkEncryptor<'_, W>> {
+        ChunkEncryptor::new(&self.cipher, inner)
+    }
+
+    /// Wrap `inner` in a reader that transparently decrypts a chunked AEAD
+    /// stream produced by [`AesEncryption::chunk_encryptor`].
+    pub fn chunk_decryptor_reader<R: Read>(&self, inner: R) -> Result<ChunkDecryptorReader<'_, R>> {
+        Ok(ChunkDecryptorReader::new(ChunkDecryptor::new(&self.cipher, inner)?))
+    }
+
+    /// Encrypt a file using the chunked AEAD framing, streaming both input
+    /// and output so the whole file never needs to be held in memory.
+    ///
+    /// Unused by the compress-then-encrypt pipeline (which drives a
+    /// `ChunkEncryptor` directly to interleave compression), but kept as a
+    /// standalone encrypt-only entry point.
+    #[allow(dead_code)]
     pub fn encrypt_file(&self, input_path: &Path, output_path: &Path) -> Result<()> {
-        // Read input file
         let mut input_file = File::open(input_path)
             .context("Failed to open input file for encryption")?;
-        let mut plaintext = Vec::new();
-        input_file
-            .read_to_end(&mut plaintext)
-            .context("Failed to read input file for encryption")?;
+        let output_file = File::create(output_path)
+            .context("Failed to create output file for encryption")?;
 
-        // Generate random nonce (12 bytes for AES-GCM)
-        let mut nonce_bytes = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
+        let mut encryptor = self.chunk_encryptor(output_file)?;
+        std::io::copy(&mut input_file, &mut encryptor)
+            .context("Failed to encrypt file")?;
+        encryptor.finish().context("Failed to finalize encryption")?;
+
+        Ok(())
+    }
+
+    /// Decrypt a file produced by [`AesEncryption::encrypt_file`], streaming
+    /// the plaintext straight to `output_path`.
+    #[allow(dead_code)]
+    pub fn decrypt_file(&self, input_path: &Path, output_path: &Path) -> Result<()> {
+        let input_file = File::open(input_path)
+            .context("Failed to open input file for decryption")?;
+        let mut reader = self.chunk_decryptor_reader(input_file)?;
+
+        let mut output_file = File::create(output_path)
+            .context("Failed to create output file for decryption")?;
+        std::io::copy(&mut reader, &mut output_file)
+            .context("Failed to decrypt file")?;
+
+        Ok(())
+    }
+}
+
+fn nonce_for(salt: &[u8; SALT_LEN], counter: u32) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..SALT_LEN].copy_from_slice(salt);
+    nonce[SALT_LEN..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
+
+fn aad_for(counter: u32, is_last: bool) -> [u8; 5] {
+    let mut aad = [0u8; 5];
+    aad[..4].copy_from_slice(&counter.to_be_bytes());
+    aad[4] = is_last as u8;
+    aad
+}
+
+/// Streaming AEAD writer implementing the chunked encryption framing:
+///
+/// ```text
+/// [8-byte salt] ([4-byte big-endian length][ciphertext])*
+/// ```
+///
+/// Each segment is encrypted with a nonce of `salt || counter` and is bound
+/// (via AAD) to its counter and whether it is the final segment, so a
+/// truncated or reordered file fails to authenticate on decrypt.
+pub struct ChunkEncryptor<'a, W: Write> {
+    cipher: &'a Aes256Gcm,
+    salt: [u8; SALT_LEN],
+    counter: u32,
+    buf: Vec<u8>,
+    inner: W,
+}
+
+impl<'a, W: Write> ChunkEncryptor<'a, W> {
+    fn new(cipher: &'a Aes256Gcm, mut inner: W) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        inner.write_all(&salt).context("Failed to write nonce salt")?;
+
+        Ok(Self {
+            cipher,
+            salt,
+            counter: 0,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            inner,
+        })
+    }
+
+    fn seal_and_write(&mut self, is_last: bool) -> Result<()> {
+        let nonce_bytes = nonce_for(&self.salt, self.counter);
+        let aad = aad_for(self.counter, is_last);
 
-        // Encrypt data
         let ciphertext = self
             .cipher
-            .encrypt(nonce, plaintext.as_ref())
-            .context("Encryption failed")?;
+            .encrypt(
+                Nonce::from_slice(&nonce_bytes),
+                Payload {
+                    msg: &self.buf,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
 
-        // Write nonce and ciphertext to output file
-        let mut output_file = File::create(output_path)
-            .context("Failed to create output file for encryption")?;
-        output_file
-            .write_all(&nonce_bytes)
-            .context("Failed to write nonce to output file")?;
-        output_file
+        self.inner
+            .write_all(&(ciphertext.len() as u32).to_be_bytes())
+            .context("Failed to write segment length")?;
+        self.inner
             .write_all(&ciphertext)
-            .context("Failed to write ciphertext to output file")?;
+            .context("Failed to write segment ciphertext")?;
 
+        self.counter += 1;
+        self.buf.clear();
         Ok(())
     }
 
-    /// Decrypt a file
-    pub fn decrypt_file(&self, input_path: &Path, output_path: &Path) -> Result<()> {
-        // Read input file
-        let mut input_file = File::open(input_path)
-            .context("Failed to open input file for decryption")?;
-        
-        // Read nonce (first 12 bytes)
-        let mut nonce_bytes = [0u8; 12];
-        input_file
-            .read_exact(&mut nonce_bytes)
-            .context("Failed to read nonce from input file")?;
+    /// Seal and flush the final (possibly empty) segment. Must be called
+    /// exactly once after all plaintext has been written.
+    pub fn finish(mut self) -> Result<()> {
+        self.seal_and_write(true)?;
+        self.inner.flush().context("Failed to flush encrypted output")?;
+        Ok(())
+    }
+}
+
+impl<'a, W: Write> Write for ChunkEncryptor<'a, W> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let mut offset = 0;
+        while offset < data.len() {
+            let space = CHUNK_SIZE - self.buf.len();
+            let take = space.min(data.len() - offset);
+            self.buf.extend_from_slice(&data[offset..offset + take]);
+            offset += take;
+
+            if self.buf.len() == CHUNK_SIZE {
+                self.seal_and_write(false)
+                    .map_err(std::io::Error::other)?;
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Pull-based reader over the chunked AEAD framing. Yields one decrypted
+/// segment per call, returning `None` once the authenticated final segment
+/// has been consumed.
+pub struct ChunkDecryptor<'a, R: Read> {
+    cipher: &'a Aes256Gcm,
+    salt: [u8; SALT_LEN],
+    counter: u32,
+    inner: R,
+    finished: bool,
+}
+
+impl<'a, R: Read> ChunkDecryptor<'a, R> {
+    fn new(cipher: &'a Aes256Gcm, mut inner: R) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        inner
+            .read_exact(&mut salt)
+            .context("Failed to read nonce salt")?;
+
+        Ok(Self {
+            cipher,
+            salt,
+            counter: 0,
+            inner,
+            finished: false,
+        })
+    }
+
+    /// Read, authenticate and decrypt the next segment.
+    pub fn next_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let mut len_bytes = [0u8; 4];
+        if let Err(e) = self.inner.read_exact(&mut len_bytes) {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                anyhow::bail!("Truncated encrypted file: missing final segment marker");
+            }
+            return Err(e).context("Failed to read segment length");
+        }
+        let len = u32::from_be_bytes(len_bytes) as usize;
+
+        let mut ciphertext = vec![0u8; len];
+        self.inner
+            .read_exact(&mut ciphertext)
+            .context("Truncated encrypted file: incomplete segment")?;
+
+        let nonce_bytes = nonce_for(&self.salt, self.counter);
         let nonce = Nonce::from_slice(&nonce_bytes);
-        
-        // Read ciphertext (rest of the file)
+
+        let (plaintext, is_last) = match self.cipher.decrypt(
+            nonce,
+            Payload {
+                msg: &ciphertext,
+                aad: &aad_for(self.counter, false),
+            },
+        ) {
+            Ok(plaintext) => (plaintext, false),
+            Err(_) => {
+                let plaintext = self
+                    .cipher
+                    .decrypt(
+                        nonce,
+                        Payload {
+                            msg: &ciphertext,
+                            aad: &aad_for(self.counter, true),
+                        },
+                    )
+                    .map_err(|_| anyhow::anyhow!("Decryption failed: wrong key or corrupted data"))?;
+                (plaintext, true)
+            }
+        };
+        self.counter += 1;
+
+        if is_last {
+            self.finished = true;
+            let mut probe = [0u8; 1];
+            if self.inner.read(&mut probe)? != 0 {
+                anyhow::bail!("Corrupted encrypted file: data found after final segment");
+            }
+        }
+
+        Ok(Some(plaintext))
+    }
+}
+
+/// Adapts [`ChunkDecryptor`] into a plain `Read` so it can sit underneath a
+/// decompressor.
+pub struct ChunkDecryptorReader<'a, R: Read> {
+    inner: ChunkDecryptor<'a, R>,
+    pending: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a, R: Read> ChunkDecryptorReader<'a, R> {
+    fn new(inner: ChunkDecryptor<'a, R>) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<'a, R: Read> Read for ChunkDecryptorReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.pending.len() {
+            match self.inner.next_chunk() {
+                Ok(Some(chunk)) => {
+                    self.pending = chunk;
+                    self.pos = 0;
+                }
+                Ok(None) => return Ok(0),
+                Err(e) => return Err(std::io::Error::other(e)),
+            }
+            if self.pending.is_empty() {
+                return Ok(0);
+            }
+        }
+
+        let available = &self.pending[self.pos..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn encrypt(aes: &AesEncryption, data: &[u8]) -> Vec<u8> {
         let mut ciphertext = Vec::new();
-        input_file
-            .read_to_end(&mut ciphertext)
-            .context("Failed to read ciphertext from input file")?;
-        
-        // Decrypt data
-        let plaintext = self
-            .cipher
-            .decrypt(nonce, ciphertext.as_ref())
-            .context("Decryption failed")?;
-        
-        // Write plaintext to output file
-        let mut output_file = File::create(output_path)
-            .context("Failed to create output file for decryption")?;
-        output_file
-            .write_all(&plaintext)
-            .context("Failed to write plaintext to output file")?;
-        
-        Ok(())
+        let mut encryptor = aes.chunk_encryptor(&mut ciphertext).unwrap();
+        encryptor.write_all(data).unwrap();
+        encryptor.finish().unwrap();
+        ciphertext
+    }
+
+    fn decrypt(aes: &AesEncryption, ciphertext: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        let mut reader = aes.chunk_decryptor_reader(Cursor::new(ciphertext)).unwrap();
+        let mut plaintext = Vec::new();
+        reader.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    fn roundtrip(data: &[u8]) -> Vec<u8> {
+        let aes = AesEncryption::new("test-key-roundtrip").unwrap();
+        decrypt(&aes, encrypt(&aes, data)).unwrap()
+    }
+
+    #[test]
+    fn roundtrip_empty_input() {
+        assert_eq!(roundtrip(b""), b"");
+    }
+
+    #[test]
+    fn roundtrip_sub_chunk_input() {
+        let data = vec![7u8; 100];
+        assert_eq!(roundtrip(&data), data);
+    }
+
+    #[test]
+    fn roundtrip_exact_chunk_boundary() {
+        let data = vec![3u8; CHUNK_SIZE];
+        assert_eq!(roundtrip(&data), data);
+    }
+
+    #[test]
+    fn roundtrip_multiple_exact_chunk_boundaries() {
+        let data = vec![9u8; CHUNK_SIZE * 2];
+        assert_eq!(roundtrip(&data), data);
+    }
+
+    #[test]
+    fn roundtrip_multi_chunk_non_aligned() {
+        let data = vec![5u8; CHUNK_SIZE * 2 + 777];
+        assert_eq!(roundtrip(&data), data);
+    }
+
+    #[test]
+    fn truncated_ciphertext_is_rejected() {
+        let aes = AesEncryption::new("test-key-roundtrip").unwrap();
+        let data = vec![1u8; CHUNK_SIZE + 500];
+        let mut ciphertext = encrypt(&aes, &data);
+
+        ciphertext.truncate(ciphertext.len() - 10);
+
+        assert!(decrypt(&aes, ciphertext).is_err());
+    }
+
+    #[test]
+    fn corrupted_ciphertext_is_rejected() {
+        let aes = AesEncryption::new("test-key-roundtrip").unwrap();
+        let mut ciphertext = encrypt(&aes, &[2u8; 100]);
+
+        // Flip a byte inside the first segment's ciphertext (past the
+        // 8-byte salt and 4-byte length prefix) so authentication fails.
+        let corrupt_index = SALT_LEN + 4;
+        ciphertext[corrupt_index] ^= 0xff;
+
+        assert!(decrypt(&aes, ciphertext).is_err());
     }
 }
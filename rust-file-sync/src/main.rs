@@ -1,28 +1,27 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::{error, info};
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver};
-use std::time::Duration;
 
 mod cloud;
 mod encryption;
 mod compression;
 mod config;
+mod manifest;
+mod pipeline;
+mod versioning;
 
 use cloud::{CloudProvider, GoogleDriveProvider, S3Provider};
 use encryption::AesEncryption;
-use compression::compress_file;
 use config::AppConfig;
+use manifest::Manifest;
+use versioning::VersionedStore;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Args {
-    /// Path to watch for changes
-    #[clap(short, long)]
-    watch_path: PathBuf,
-
     /// Cloud provider to use (s3 or gdrive)
     #[clap(short, long, default_value = "gdrive")]
     provider: String,
@@ -30,6 +29,33 @@ struct Args {
     /// Configuration file path
     #[clap(short, long, default_value = "config.toml")]
     config: PathBuf,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Watch a directory and sync changed files to the cloud
+    Watch {
+        /// Path to watch for changes
+        #[clap(short, long)]
+        watch_path: PathBuf,
+    },
+    /// List the generations available for a versioned object
+    Generations {
+        /// Logical object name (the file name it was uploaded under)
+        name: String,
+    },
+    /// Restore (download) a specific generation of a versioned object
+    Restore {
+        /// Logical object name (the file name it was uploaded under)
+        name: String,
+        /// Generation number to restore
+        generation: u64,
+        /// Local path to write the restored file to
+        output: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
@@ -42,21 +68,46 @@ fn main() -> Result<()> {
 
     // Parse command line arguments
     let args = Args::parse();
-    
+
     // Load configuration
     let config = config::load_config(&args.config)
         .context("Failed to load configuration")?;
-    
+
     // Create cloud provider
     let provider: Box<dyn CloudProvider> = match args.provider.as_str() {
         "s3" => Box::new(S3Provider::new(&config.s3)?),
         "gdrive" => Box::new(GoogleDriveProvider::new(&config.google_drive)?),
         _ => anyhow::bail!("Unsupported cloud provider: {}", args.provider),
     };
-    
+
+    match args.command {
+        Command::Watch { watch_path } => run_watch(watch_path, provider, &config),
+        Command::Generations { name } => list_generations(&name, provider, &config),
+        Command::Restore {
+            name,
+            generation,
+            output,
+        } => restore_generation(&name, generation, &output, provider, &config),
+    }
+}
+
+fn run_watch(watch_path: PathBuf, provider: Box<dyn CloudProvider>, config: &AppConfig) -> Result<()> {
     // Create encryption service
     let encryption = AesEncryption::new(&config.encryption.key)?;
-    
+
+    // The watcher reports absolute paths, so resolve the watch path the same
+    // way before deriving the manifest path compared against them.
+    let watch_path = if watch_path.is_absolute() {
+        watch_path
+    } else {
+        std::env::current_dir()?.join(watch_path)
+    };
+
+    // Load the content-hash manifest so unchanged files aren't re-uploaded
+    let manifest_path = watch_path.join(manifest::MANIFEST_FILE_NAME);
+    let manifest = Manifest::load(&manifest_path)
+        .context("Failed to load sync manifest")?;
+
     // Start file watcher
     let (tx, rx) = channel();
     let mut watcher = RecommendedWatcher::new(
@@ -69,30 +120,75 @@ fn main() -> Result<()> {
     )?;
 
     // Watch the directory recursively
-    watcher.watch(args.watch_path.as_path(), RecursiveMode::Recursive)?;
-    info!("Watching directory: {:?}", args.watch_path);
+    watcher.watch(watch_path.as_path(), RecursiveMode::Recursive)?;
+    info!("Watching directory: {:?}", watch_path);
 
     // Process events
-    handle_events(rx, &provider, &encryption, &config)?;
+    handle_events(rx, provider.as_ref(), &encryption, config, manifest, &manifest_path)?;
+
+    Ok(())
+}
+
+fn list_generations(name: &str, provider: Box<dyn CloudProvider>, config: &AppConfig) -> Result<()> {
+    let store = VersionedStore::new(provider.as_ref(), config.versioning.retention_count);
+    let generations = store.list_generations(&config.remote_base_path, name)?;
 
+    if generations.is_empty() {
+        println!("No generations found for {}", name);
+    } else {
+        println!("Generations for {} (newest first):", name);
+        for generation in generations {
+            println!("  {}", generation);
+        }
+    }
+    Ok(())
+}
+
+fn restore_generation(
+    name: &str,
+    generation: u64,
+    output: &Path,
+    provider: Box<dyn CloudProvider>,
+    config: &AppConfig,
+) -> Result<()> {
+    let encryption = AesEncryption::new(&config.encryption.key)?;
+    let store = VersionedStore::new(provider.as_ref(), config.versioning.retention_count);
+    store.restore_generation(&config.remote_base_path, name, generation, &encryption, output)?;
+    info!(
+        "Restored generation {} of {} to {:?}",
+        generation, name, output
+    );
     Ok(())
 }
 
 fn handle_events(
     rx: Receiver<Event>,
-    provider: &Box<dyn CloudProvider>,
+    provider: &dyn CloudProvider,
     encryption: &AesEncryption,
     config: &AppConfig,
+    mut manifest: Manifest,
+    manifest_path: &Path,
 ) -> Result<()> {
     for event in rx {
         match event.kind {
             notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
                 for path in event.paths {
+                    // Don't react to our own manifest writes.
+                    if path == manifest_path {
+                        continue;
+                    }
                     if path.is_file() {
                         info!("File changed: {:?}", path);
-                        
+
                         // Process and upload the file
-                        match process_and_upload_file(&path, provider, encryption, config) {
+                        match process_and_upload_file(
+                            &path,
+                            provider,
+                            encryption,
+                            config,
+                            &mut manifest,
+                            manifest_path,
+                        ) {
                             Ok(_) => info!("Successfully processed and uploaded: {:?}", path),
                             Err(e) => error!("Failed to process file {:?}: {}", path, e),
                         }
@@ -113,30 +209,65 @@ fn handle_events(
 
 fn process_and_upload_file(
     path: &Path,
-    provider: &Box<dyn CloudProvider>,
+    provider: &dyn CloudProvider,
     encryption: &AesEncryption,
     config: &AppConfig,
+    manifest: &mut Manifest,
+    manifest_path: &Path,
 ) -> Result<()> {
-    // Create a temporary file for processing
     let temp_dir = std::env::temp_dir();
     let file_name = path.file_name().context("Invalid file name")?;
-    let temp_path = temp_dir.join(file_name);
-    
-    // Compress the file
-    let compressed_path = temp_dir.join(format!("{}.gz", file_name.to_string_lossy()));
-    compress_file(path, &compressed_path)?;
-    
-    // Encrypt the compressed file
-    let encrypted_path = temp_dir.join(format!("{}.enc", file_name.to_string_lossy()));
-    encryption.encrypt_file(&compressed_path, &encrypted_path)?;
-    
-    // Upload to cloud
-    let remote_path = format!("{}/{}", config.remote_base_path, file_name.to_string_lossy());
-    provider.upload_file(&encrypted_path, &remote_path)?;
-    
-    // Clean up temporary files
-    std::fs::remove_file(&compressed_path)?;
-    std::fs::remove_file(&encrypted_path)?;
-    
+
+    // Skip the work entirely if the file's content hasn't changed since
+    // its last successful upload (editors rewriting identical bytes,
+    // duplicate save events, etc).
+    let content_hash = manifest::hash_file(path)?;
+    if manifest.is_unchanged(path, &content_hash) {
+        info!("File unchanged, skipping: {:?}", path);
+        return Ok(());
+    }
+
+    // Stream compress -> encrypt in a single pass so multi-gigabyte files
+    // never need to be buffered whole in memory or written twice to disk.
+    let staged_path = temp_dir.join(format!("{}.enc", file_name.to_string_lossy()));
+    pipeline::compress_and_encrypt_file(path, &staged_path, encryption, &config.compression)?;
+    let compression_tag = config.compression.algorithm.tag();
+
+    let remote_path = if config.versioning.enabled {
+        // Upload as a new generation, keeping previous ones around (up to
+        // the retention count) instead of overwriting them.
+        let store = VersionedStore::new(provider, config.versioning.retention_count);
+        let name = file_name.to_string_lossy().into_owned();
+        let generation = store.upload_new_generation(
+            &staged_path,
+            &config.remote_base_path,
+            &name,
+            &content_hash,
+            compression_tag,
+        )?;
+        VersionedStore::generation_key(&config.remote_base_path, &name, generation, compression_tag)
+    } else {
+        // Fold a short content fingerprint and the compression algorithm
+        // into the key: different versions of the same logical file get
+        // distinct names, and a later download knows which decoder to use.
+        let remote_path = format!(
+            "{}/{}-{}.{}",
+            config.remote_base_path,
+            manifest::short_fingerprint(&content_hash),
+            file_name.to_string_lossy(),
+            compression_tag
+        );
+        provider.upload_file(&staged_path, &remote_path)?;
+        remote_path
+    };
+
+    // Clean up the staged file
+    std::fs::remove_file(&staged_path)?;
+
+    // Only record the upload once it has actually succeeded, so a failed
+    // upload is retried next time.
+    manifest.record(path, content_hash, remote_path);
+    manifest.save(manifest_path)?;
+
     Ok(())
 }
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Name of the manifest file maintained inside the watched directory.
+pub const MANIFEST_FILE_NAME: &str = ".ai_sync_manifest.json";
+
+/// One tracked file's last-known content hash and where it was uploaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub content_hash: String,
+    pub remote_key: String,
+}
+
+/// Maps watched file paths to the hash/remote key of their last successful
+/// upload, so unchanged files (editors rewriting identical bytes,
+/// duplicate save events) don't trigger a redundant compress/encrypt/upload.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    entries: HashMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    /// Load the manifest from `path`, starting with an empty one if it
+    /// doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {:?}", path))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse manifest {:?}", path))
+    }
+
+    /// Persist the manifest to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents =
+            serde_json::to_string_pretty(self).context("Failed to serialize manifest")?;
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write manifest {:?}", path))
+    }
+
+    /// Returns `true` if `path`'s last recorded content hash matches
+    /// `content_hash`, i.e. the file is unchanged since its last upload.
+    pub fn is_unchanged(&self, path: &Path, content_hash: &str) -> bool {
+        self.entries
+            .get(&path_key(path))
+            .is_some_and(|entry| entry.content_hash == content_hash)
+    }
+
+    /// Record a successful upload so the file is skipped next time it's
+    /// seen unchanged. Callers should only do this after the upload
+    /// succeeds, so a failed upload is retried next time.
+    pub fn record(&mut self, path: &Path, content_hash: String, remote_key: String) {
+        self.entries.insert(
+            path_key(path),
+            ManifestEntry {
+                content_hash,
+                remote_key,
+            },
+        );
+    }
+}
+
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Stream-hash a file's contents with SHA-256 without loading it into
+/// memory.
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open file {:?} for hashing", path))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to hash file {:?}", path))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Short fingerprint (first 8 bytes, 16 hex chars) of a content hash,
+/// suitable for folding into a remote object name so different versions of
+/// the same logical file get distinct keys.
+pub fn short_fingerprint(content_hash: &str) -> &str {
+    &content_hash[..16.min(content_hash.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_unchanged_matches_recorded_hash() {
+        let mut manifest = Manifest::default();
+        let path = Path::new("/tmp/model.bin");
+        manifest.record(path, "abc123".to_string(), "remote/model.bin".to_string());
+
+        assert!(manifest.is_unchanged(path, "abc123"));
+        assert!(!manifest.is_unchanged(path, "different"));
+    }
+
+    #[test]
+    fn is_unchanged_is_false_for_untracked_path() {
+        let manifest = Manifest::default();
+        assert!(!manifest.is_unchanged(Path::new("/tmp/never-seen.bin"), "abc123"));
+    }
+
+    #[test]
+    fn record_overwrites_the_previous_entry_for_the_same_path() {
+        let mut manifest = Manifest::default();
+        let path = Path::new("/tmp/model.bin");
+        manifest.record(path, "first".to_string(), "remote/v1".to_string());
+        manifest.record(path, "second".to_string(), "remote/v2".to_string());
+
+        assert!(manifest.is_unchanged(path, "second"));
+        assert!(!manifest.is_unchanged(path, "first"));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut manifest = Manifest::default();
+        manifest.record(
+            Path::new("/tmp/model.bin"),
+            "abc123".to_string(),
+            "remote/model.bin".to_string(),
+        );
+
+        let path = std::env::temp_dir().join(format!("manifest-test-{}.json", std::process::id()));
+        manifest.save(&path).unwrap();
+        let loaded = Manifest::load(&path).unwrap();
+
+        assert!(loaded.is_unchanged(Path::new("/tmp/model.bin"), "abc123"));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn load_missing_manifest_starts_empty() {
+        let path = std::env::temp_dir().join("manifest-test-does-not-exist.json");
+        let manifest = Manifest::load(&path).unwrap();
+        assert!(!manifest.is_unchanged(Path::new("/tmp/anything"), "abc123"));
+    }
+
+    #[test]
+    fn hash_file_is_stable_and_sensitive_to_content() {
+        let dir = std::env::temp_dir();
+        let path_a = dir.join(format!("manifest-test-hash-a-{}.bin", std::process::id()));
+        let path_b = dir.join(format!("manifest-test-hash-b-{}.bin", std::process::id()));
+        std::fs::write(&path_a, b"hello world").unwrap();
+        std::fs::write(&path_b, b"goodbye world").unwrap();
+
+        let hash_a1 = hash_file(&path_a).unwrap();
+        let hash_a2 = hash_file(&path_a).unwrap();
+        let hash_b = hash_file(&path_b).unwrap();
+
+        assert_eq!(hash_a1, hash_a2);
+        assert_ne!(hash_a1, hash_b);
+
+        std::fs::remove_file(&path_a).ok();
+        std::fs::remove_file(&path_b).ok();
+    }
+
+    #[test]
+    fn short_fingerprint_truncates_to_16_hex_chars() {
+        let hash = "a".repeat(64);
+        assert_eq!(short_fingerprint(&hash), "a".repeat(16));
+    }
+
+    #[test]
+    fn short_fingerprint_handles_shorter_than_16_input() {
+        assert_eq!(short_fingerprint("abcd"), "abcd");
+        assert_eq!(short_fingerprint(""), "");
+    }
+}
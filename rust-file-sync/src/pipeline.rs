@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::Path;
+
+use crate::compression::{CompressReader, CompressWriter, CompressionAlgorithm};
+use crate::config::CompressionConfig;
+use crate::encryption::AesEncryption;
+
+/// Stream a file through compress -> encrypt in one pass, without ever
+/// holding the whole file in memory: at most one encryption chunk (and its
+/// ciphertext) is buffered at a time.
+///
+/// This replaces the old two-temp-file approach (`compress_file` followed
+/// by `AesEncryption::encrypt_file`), which doubled disk writes and could
+/// not handle files larger than available memory.
+pub fn compress_and_encrypt_file(
+    input_path: &Path,
+    output_path: &Path,
+    encryption: &AesEncryption,
+    compression: &CompressionConfig,
+) -> Result<()> {
+    let mut input_file =
+        File::open(input_path).context("Failed to open input file for pipeline")?;
+    let output_file =
+        File::create(output_path).context("Failed to create output file for pipeline")?;
+
+    let encryptor = encryption.chunk_encryptor(output_file)?;
+    let mut compressor = CompressWriter::new(compression, encryptor)?;
+
+    std::io::copy(&mut input_file, &mut compressor).context("Failed to compress file")?;
+    let encryptor = compressor.finish().context("Failed to finish compression stream")?;
+    encryptor.finish().context("Failed to finish encryption stream")?;
+
+    log::info!(
+        "Compressed ({:?}) and encrypted file: {:?} -> {:?}",
+        compression.algorithm,
+        input_path,
+        output_path
+    );
+    Ok(())
+}
+
+/// Inverse of [`compress_and_encrypt_file`]: decrypt then decompress with
+/// `algorithm`, streaming straight to `output_path`.
+pub fn decrypt_and_decompress_file(
+    input_path: &Path,
+    output_path: &Path,
+    encryption: &AesEncryption,
+    algorithm: CompressionAlgorithm,
+) -> Result<()> {
+    let input_file =
+        File::open(input_path).context("Failed to open input file for pipeline")?;
+    let decryptor = encryption.chunk_decryptor_reader(input_file)?;
+    let mut decoder = CompressReader::new(algorithm, decryptor)?;
+
+    let mut output_file =
+        File::create(output_path).context("Failed to create output file for pipeline")?;
+    std::io::copy(&mut decoder, &mut output_file).context("Failed to decompress file")?;
+
+    log::info!(
+        "Decrypted and decompressed file: {:?} -> {:?}",
+        input_path,
+        output_path
+    );
+    Ok(())
+}